@@ -0,0 +1,278 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static BASH_TIMESTAMP_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("^#[0-9]{8}[0-9]*$").unwrap());
+static ZSH_LINE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("^: ([0-9]{8}[0-9]*):([0-9]*);(.*)$").unwrap());
+
+/// Which on-disk history format we're reading. `post_process` writes the output back out in the
+/// same format it detected on input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryFormat {
+    Bash,
+    ZshExtended,
+    Fish,
+}
+
+/// A `(timestamp, command)` pair read from a history file. `command` retains its trailing
+/// newline, since that's what `post_process` writes straight back out.
+pub type Entry = (u32, String);
+
+/// Parses one on-disk shell history format into a stream of `(timestamp, command)` entries.
+/// Implementations are picked by [`detect_format`] scanning the file's lines for a
+/// format-specific marker, rather than by branching on format inside the parsing logic itself.
+pub trait Importer {
+    /// Reports whether `line` is this format's marker (e.g. a zsh `: <ts>:<elapsed>;` prefix, or
+    /// a fish `- cmd: ` record start).
+    fn detect_line(line: &str) -> bool
+    where
+        Self: Sized;
+
+    /// Parses commands out of `lines`, in file order. Pulls from `lines` lazily, so a caller
+    /// driving the returned iterator one entry at a time never holds more than the current
+    /// command in memory.
+    fn parse(&self, lines: impl Iterator<Item = String>) -> impl Iterator<Item = Entry>;
+}
+
+/// Picks the importer to use by scanning `lines` for a format-specific marker, stopping at the
+/// first one found. Some formats (zsh in particular) can have a prefix of untagged history
+/// predating the marker being enabled, so this scans as far as it needs to -- all the way to EOF
+/// if nothing matches -- rather than giving up after a handful of lines and silently mis-parsing
+/// the rest of the file. Falls back to bash/plain, which has no marker of its own, only once the
+/// whole file has been scanned with no match.
+pub fn detect_format(lines: impl Iterator<Item = String>) -> HistoryFormat {
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if FishImporter::detect_line(&line) {
+            return HistoryFormat::Fish;
+        }
+        if ZshImporter::detect_line(&line) {
+            return HistoryFormat::ZshExtended;
+        }
+    }
+    HistoryFormat::Bash
+}
+
+/// Plain bash history, with or without the `#<timestamp>` lines that `HISTTIMEFORMAT` adds:
+///
+/// ```text
+/// #1746142083
+/// cargo build --workspace --profile release
+/// ```
+pub struct BashImporter;
+
+impl Importer for BashImporter {
+    fn detect_line(_line: &str) -> bool {
+        // No format-specific marker; this is the fallback when nothing else matches.
+        false
+    }
+
+    fn parse(&self, lines: impl Iterator<Item = String>) -> impl Iterator<Item = Entry> {
+        let mut lines = lines;
+        let mut timestamp = 0u32;
+        let mut command = String::new();
+
+        std::iter::from_fn(move || {
+            for line in lines.by_ref() {
+                if let Some(new_timestamp) = parse_bash_timestamp(&line) {
+                    // We've found the timestamp for the next command. So emit the existing
+                    // command with the previous timestamp, if there is one.
+                    let previous_timestamp = std::mem::replace(&mut timestamp, new_timestamp);
+                    if !command.is_empty() {
+                        return Some((previous_timestamp, std::mem::take(&mut command)));
+                    }
+                } else {
+                    command = format!("{command}{line}\n");
+                }
+            }
+
+            // Because the loop above only emits a command when it sees the next command's
+            // timestamp, the final command never gets emitted that way. So do that now, once.
+            (!command.is_empty()).then(|| (timestamp, std::mem::take(&mut command)))
+        })
+    }
+}
+
+fn parse_bash_timestamp(line: &str) -> Option<u32> {
+    if BASH_TIMESTAMP_REGEX.is_match(line) {
+        Some(line[1..].parse().unwrap())
+    } else {
+        None
+    }
+}
+
+/// zsh `EXTENDED_HISTORY` format:
+///
+/// ```text
+/// : 1746142083:0;cargo build --workspace --profile release
+/// ```
+pub struct ZshImporter;
+
+impl Importer for ZshImporter {
+    fn detect_line(line: &str) -> bool {
+        ZSH_LINE_REGEX.is_match(line)
+    }
+
+    fn parse(&self, lines: impl Iterator<Item = String>) -> impl Iterator<Item = Entry> {
+        let mut lines = lines;
+
+        std::iter::from_fn(move || {
+            for line in lines.by_ref() {
+                if let Some(captures) = ZSH_LINE_REGEX.captures(&line) {
+                    let timestamp = captures[1].parse::<u32>().unwrap();
+                    // zsh (on my system at least) seems to ignore the execution time field; it is
+                    // always 0.
+                    // let execution_time = captures[2].parse::<u32>()?;
+                    let mut command = captures[3].trim().to_string();
+
+                    // Multi-line commands have escaped newlines
+                    while command.ends_with('\\') {
+                        match lines.next() {
+                            Some(continuation) => command = format!("{command}\n{continuation}"),
+                            None => break,
+                        }
+                    }
+
+                    return Some((timestamp, command + "\n"));
+                }
+            }
+            None
+        })
+    }
+}
+
+/// fish history, a sequence of YAML-ish records:
+///
+/// ```text
+/// - cmd: cargo build --workspace --profile release
+///   when: 1746142083
+///   paths:
+///     - Cargo.toml
+/// ```
+pub struct FishImporter;
+
+impl Importer for FishImporter {
+    fn detect_line(line: &str) -> bool {
+        line.starts_with("- cmd: ")
+    }
+
+    fn parse(&self, lines: impl Iterator<Item = String>) -> impl Iterator<Item = Entry> {
+        let mut lines = lines;
+        let mut pending_cmd: Option<String> = None;
+        let mut pending_timestamp: Option<u32> = None;
+
+        std::iter::from_fn(move || {
+            for line in lines.by_ref() {
+                if let Some(cmd) = line.strip_prefix("- cmd: ") {
+                    let finished = flush_fish_entry(&mut pending_cmd, &mut pending_timestamp);
+                    pending_cmd = Some(cmd.to_string());
+                    if finished.is_some() {
+                        return finished;
+                    }
+                } else if let Some(when) = line.strip_prefix("  when: ") {
+                    pending_timestamp = when.trim().parse::<u32>().ok();
+                } else if pending_timestamp.is_none() {
+                    // Not a "when:" line yet, so this is a continuation of a multi-line "cmd:".
+                    if let Some(command) = pending_cmd.as_mut() {
+                        command.push('\n');
+                        command.push_str(&line);
+                    }
+                }
+                // Otherwise it's a "paths:" block entry, which we don't need to preserve.
+            }
+            flush_fish_entry(&mut pending_cmd, &mut pending_timestamp)
+        })
+    }
+}
+
+fn flush_fish_entry(pending_cmd: &mut Option<String>, pending_timestamp: &mut Option<u32>) -> Option<Entry> {
+    match (pending_cmd.take(), pending_timestamp.take()) {
+        (Some(command), Some(timestamp)) => Some((timestamp, command + "\n")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines_of(text: &str) -> impl Iterator<Item = String> {
+        text.lines().map(String::from).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn bash_parses_timestamped_commands() {
+        let entries: Vec<Entry> = BashImporter.parse(lines_of("#1746142083\ncargo build\n#1746142090\ngit status\n")).collect();
+
+        assert_eq!(entries, vec![(1746142083, "cargo build\n".to_string()), (1746142090, "git status\n".to_string())]);
+    }
+
+    #[test]
+    fn bash_joins_multi_line_commands_under_one_timestamp() {
+        let entries: Vec<Entry> = BashImporter.parse(lines_of("#1746142083\ncat <<EOF\nhello\nEOF\n")).collect();
+
+        assert_eq!(entries, vec![(1746142083, "cat <<EOF\nhello\nEOF\n".to_string())]);
+    }
+
+    #[test]
+    fn zsh_parses_extended_history_lines() {
+        let entries: Vec<Entry> =
+            ZshImporter.parse(lines_of(": 1746142083:0;cargo build\n: 1746142090:0;git status\n")).collect();
+
+        assert_eq!(entries, vec![(1746142083, "cargo build\n".to_string()), (1746142090, "git status\n".to_string())]);
+    }
+
+    #[test]
+    fn zsh_joins_backslash_continuations() {
+        let entries: Vec<Entry> = ZshImporter.parse(lines_of(": 1746142083:0;echo one \\\ntwo\n")).collect();
+
+        assert_eq!(entries, vec![(1746142083, "echo one \\\ntwo\n".to_string())]);
+    }
+
+    #[test]
+    fn fish_parses_cmd_and_when() {
+        let entries: Vec<Entry> =
+            FishImporter.parse(lines_of("- cmd: cargo build\n  when: 1746142083\n- cmd: git status\n  when: 1746142090\n")).collect();
+
+        assert_eq!(entries, vec![(1746142083, "cargo build\n".to_string()), (1746142090, "git status\n".to_string())]);
+    }
+
+    #[test]
+    fn fish_joins_multi_line_cmd_continuation() {
+        let entries: Vec<Entry> = FishImporter.parse(lines_of("- cmd: echo one\ntwo\n  when: 1746142083\n")).collect();
+
+        assert_eq!(entries, vec![(1746142083, "echo one\ntwo\n".to_string())]);
+    }
+
+    #[test]
+    fn fish_skips_paths_block() {
+        let entries: Vec<Entry> = FishImporter
+            .parse(lines_of("- cmd: cargo build\n  when: 1746142083\n  paths:\n    - Cargo.toml\n    - Cargo.lock\n"))
+            .collect();
+
+        assert_eq!(entries, vec![(1746142083, "cargo build\n".to_string())]);
+    }
+
+    #[test]
+    fn detect_format_finds_zsh_marker_after_untagged_prefix() {
+        let mut lines: Vec<String> = (0..25).map(|i| format!("untagged_old_command_{i}")).collect();
+        lines.push(": 1746142083:0;cargo build".to_string());
+
+        assert_eq!(detect_format(lines.into_iter()), HistoryFormat::ZshExtended);
+    }
+
+    #[test]
+    fn detect_format_finds_fish_marker() {
+        let lines = vec!["- cmd: cargo build".to_string(), "  when: 1746142083".to_string()];
+
+        assert_eq!(detect_format(lines.into_iter()), HistoryFormat::Fish);
+    }
+
+    #[test]
+    fn detect_format_falls_back_to_bash_when_exhausted() {
+        let lines = vec!["cargo build".to_string(), "git status".to_string()];
+
+        assert_eq!(detect_format(lines.into_iter()), HistoryFormat::Bash);
+    }
+}
@@ -1,20 +1,34 @@
-use std::collections::HashSet;
+mod config;
+mod entropy;
+mod importer;
+mod stats;
+
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufRead, BufReader, IsTerminal, Write};
 use std::path::Path;
-use std::{env, fs};
 
 use anyhow::Result;
 use btreemultimap::BTreeMultiMap;
-use clap::{command, Parser};
+use clap::{Parser, Subcommand, ValueEnum};
 use home::home_dir;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use log::{debug, info, trace, LevelFilter};
-use once_cell::sync::Lazy;
-use regex::Regex;
 use simple_logger::SimpleLogger;
 
-static BASH_TIMESTAMP_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("^#[0-9]{8}[0-9]*$").unwrap());
-static ZSH_LINE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("^: ([0-9]{8}[0-9]*):([0-9]*);(.*)$").unwrap());
+use config::Config;
+use importer::{detect_format, BashImporter, Entry, FishImporter, HistoryFormat, Importer, ZshImporter};
+
+/// Which occurrence of a duplicate command to keep, when more than one survives filtering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Keep {
+    /// Keep the command at the timestamp it was first seen.
+    First,
+    /// Keep the command at the timestamp it was most recently seen, so the history stays
+    /// recency-ranked for interactive recall.
+    Latest,
+}
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -25,9 +39,10 @@ static ZSH_LINE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("^: ([0-9]{8}[0-9]*
 ///
 /// - scrape the file of anything confidential (passwords, etc.)
 pub struct Args {
-    /// Only preserve commands greater than this length
-    #[arg(short, long, default_value = "15")]
-    pub min_length: u16,
+    /// Only preserve commands at least this long [default: 15, or the config file's
+    /// min_length if set]
+    #[arg(short, long)]
+    pub min_length: Option<u16>,
     /// Logging level. Default: Info. Valid values: Off, Error, Warn, Info, Debug, Trace.
     #[arg(short, long, default_value = "info", global = true)]
     pub log: LevelFilter,
@@ -38,6 +53,22 @@ pub struct Args {
     /// Name of the output file.
     #[arg(short, long, default_value = "shrunk_history")]
     pub output: String,
+    /// Path to a TOML or YAML config file (by extension) supplying exclude/replacement/flag rules, merged with the
+    /// built-in defaults [default: ~/.config/history-shrinker/config.toml, if it exists]
+    #[arg(short, long)]
+    pub config: Option<String>,
+    /// Which occurrence of a duplicate command to keep.
+    #[arg(short, long, default_value = "first")]
+    pub keep: Keep,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print a summary of the history file (top commands, redaction/flag counts, activity by day
+    /// and hour) instead of writing a shrunk copy.
+    Stats,
 }
 
 fn main() -> Result<()> {
@@ -45,6 +76,11 @@ fn main() -> Result<()> {
 
     SimpleLogger::new().with_level(args.log).init().unwrap();
 
+    let mut config = Config::load(args.config.as_deref())?;
+    if let Some(min_length) = args.min_length {
+        config.min_length = min_length;
+    }
+
     let history_file = if let Some(input_arg) = args.input {
         Path::new(&input_arg).into()
     } else if let Ok(env_var) = env::var("HISTFILE") {
@@ -53,169 +89,124 @@ fn main() -> Result<()> {
         home_dir().unwrap().join(".bash_history")
     };
 
-    // Slurp the whole file into a string.
-    let contents = fs::read_to_string(history_file)?;
-    let lines = contents.lines();
-    let lines: Vec<&str> = lines.collect();
-    // The map that stores the commands that we will write out to the reduced history file.
-    let mut command_map: BTreeMultiMap<u32, String> = BTreeMultiMap::new();
-    // This set is used to strip out duplicate commands from the history.
-    let mut commands_seen: HashSet<String> = HashSet::new();
-    // And let's keep track of the largest commands, too.
-    let mut big_commands: BTreeMultiMap<usize, String> = BTreeMultiMap::new();
-    let mut flagged_commands: HashSet<String> = HashSet::new();
-
-    // What type of history file is it?
-    let is_zsh = is_zsh_extended(&lines);
-    
-    if is_zsh {
-        process_zsh_history(
-            lines,
-            &mut command_map,
-            &mut commands_seen,
-            &mut big_commands,
-            &mut flagged_commands,
-        )?;
-    } else {
-        process_bash_history(
-            lines,
-            &mut command_map,
-            &mut commands_seen,
-            &mut big_commands,
-            &mut flagged_commands,
-        )?;
+    // Figure out which importer to use by scanning the whole file for a format marker up front.
+    // This is a second full read of the file when it turns out to be plain bash (no marker
+    // anywhere), but it's a cheap line-by-line pass, and it means a zsh/fish file with untagged
+    // history at the top (predating EXTENDED_HISTORY or fish's YAML format being enabled) still
+    // gets detected correctly instead of silently mis-parsed as bash.
+    let detect_reader = BufReader::new(File::open(&history_file)?);
+    let format = detect_format(detect_reader.lines().map_while(|line| line.ok()));
+
+    let file = File::open(&history_file)?;
+    let file_len = file.metadata()?.len();
+
+    // Read line-by-line instead of slurping the whole file, so peak memory is bounded by the
+    // dedup set rather than the file itself. A progress bar tracks our position by bytes read;
+    // it's hidden (but still cheap to update) when stderr isn't a terminal.
+    let progress = ProgressBar::new(file_len);
+    progress.set_style(ProgressStyle::with_template("{bar:40.cyan/blue} {bytes}/{total_bytes} ({eta})").unwrap());
+    if !std::io::stderr().is_terminal() {
+        progress.set_draw_target(ProgressDrawTarget::hidden());
     }
+    let lines = BufReader::new(progress.wrap_read(file)).lines().map_while(|line| line.ok());
 
-    post_process(&args.output, is_zsh, command_map, big_commands, flagged_commands)?;
+    let entries: Box<dyn Iterator<Item = Entry>> = match format {
+        HistoryFormat::Bash => Box::new(BashImporter.parse(lines)),
+        HistoryFormat::ZshExtended => Box::new(ZshImporter.parse(lines)),
+        HistoryFormat::Fish => Box::new(FishImporter.parse(lines)),
+    };
 
-    Ok(())
-}
+    let mut store = CommandStore::default();
+    for (timestamp, command) in entries {
+        add_command(timestamp, &command, &config, args.keep, &mut store);
+    }
+    progress.finish_and_clear();
 
-fn is_zsh_extended(lines: &Vec<&str>) -> bool {
-    // zsh EXTENDED_HISTORY format:
-    // : 1746142083:0;cargo build --workspace --profile release
-    //
-    // bash format:
-    // # 1746142083
-    // cargo build --workspace --profile release
-    //
-    // plain format:
-    // cargo build --workspace --profile release
-    //
-    // With or without the #timestamp lines, we can process as a bash history file.
-    // So we only care if it's zsh extended or not.
-
-    for &line in lines {
-        if ZSH_LINE_REGEX.is_match(line) {
-            return true;
-        }
+    match args.command {
+        Some(Command::Stats) => stats::print_stats(&store, file_len as usize),
+        None => post_process(&args.output, format, store)?,
     }
 
-    false
+    Ok(())
 }
 
-fn process_zsh_history(
-    lines: Vec<&str>,
-    command_map: &mut BTreeMultiMap<u32, String>,
-    commands_seen: &mut HashSet<String>,
-    big_commands: &mut BTreeMultiMap<usize, String>,
-    flagged_commands: &mut HashSet<String>,
-) -> Result<()> {
-    let mut iter = lines.into_iter();
-    while let Some(line) = iter.next() {
-        if ZSH_LINE_REGEX.is_match(line) {
-            let captures = ZSH_LINE_REGEX.captures(line).unwrap();
-            let timestamp = captures[1].parse::<u32>()?;
-            // zsh (on my system at least) seems to ignore the execution time field; it is always 0.
-            // let execution_time = captures[2].parse::<u32>()?;
-            let mut command = captures[3].trim().to_string();
-            
-            // Multi-line commands have escaped newlines
-            while command.ends_with('\\') {
-                if let Some(continuation) = iter.next() {
-                    command = format!("{command}\n{continuation}");
-                } else {
-                    break;
-                }
-            }
-            
-            add_command(timestamp, &(command + "\n"), command_map, commands_seen, big_commands, flagged_commands);
-        }
-    }
-    
-    Ok(())
+/// The filtered commands destined for the output file, plus the bookkeeping state used to filter,
+/// deduplicate, and (for `stats` mode) summarize them.
+#[derive(Default)]
+pub(crate) struct CommandStore {
+    pub(crate) command_map: BTreeMultiMap<u32, String>,
+    // Maps a filtered command to the timestamp it was stored under, so a later duplicate can be
+    // compared against (and, in `Keep::Latest` mode, used to relocate) the earlier one.
+    pub(crate) commands_seen: HashMap<String, u32>,
+    big_commands: BTreeMultiMap<usize, String>,
+    pub(crate) flagged_commands: HashSet<String>,
+    // Stats-mode bookkeeping: every command the importer handed us (before exclusion/dedup),
+    // bucketed by its first word, plus how many were scrubbed by the entropy scanner.
+    pub(crate) total_lines: usize,
+    pub(crate) first_token_counts: HashMap<String, usize>,
+    pub(crate) count_redacted: usize,
 }
 
-fn process_bash_history(
-    lines: Vec<&str>,
-    command_map: &mut BTreeMultiMap<u32, String>,
-    commands_seen: &mut HashSet<String>,
-    big_commands: &mut BTreeMultiMap<usize, String>,
-    flagged_commands: &mut HashSet<String>,
-) -> Result<()> {
-    let mut timestamp = 0u32;
-    let mut command = String::new();
-
-    for line in lines {
-        if let Some(new_timestamp) = parse_timestamp(line) {
-            // We've found the timestamp for the next command. So add the existing
-            // command with the previous timestamp;
-
-            add_command(
-                timestamp,
-                &command,
-                command_map,
-                commands_seen,
-                big_commands,
-                flagged_commands,
-            );
-            timestamp = new_timestamp;
-            command = String::new();
-        } else {
-            command = format!("{command}{line}\n");
-        }
+fn add_command(timestamp: u32, command: &str, config: &Config, keep: Keep, store: &mut CommandStore) {
+    if command.is_empty() {
+        return;
     }
 
-    // Because in the above loop we only add a command when we see the next command's timestamp, we
-    // won't have added the final command. So do that now.
-    add_command(timestamp, &command, command_map, commands_seen, big_commands, flagged_commands);
-    
-    Ok(())
-}
+    let trimmed = command.trim();
+    store.total_lines += 1;
+    if let Some(first_token) = trimmed.split_whitespace().next() {
+        *store.first_token_counts.entry(first_token.to_string()).or_insert(0) += 1;
+    }
+
+    if (trimmed.len() as u16) < config.min_length {
+        return;
+    }
+
+    let mut filtered_command = filter_command(command, config);
+
+    if let Some(redacted) = entropy::redact_high_entropy_tokens(&filtered_command, config) {
+        store.flagged_commands.insert(format!("Flagged for high-entropy secret: {}", filtered_command.trim_end()));
+        store.count_redacted += 1;
+        filtered_command = redacted;
+    }
 
-fn add_command(
-    timestamp: u32,
-    command: &str,
-    command_map: &mut BTreeMultiMap<u32, String>,
-    commands_seen: &mut HashSet<String>,
-    big_commands: &mut BTreeMultiMap<usize, String>,
-    flagged_commands: &mut HashSet<String>,
-) {
-    if !command.is_empty() {
-        let filtered_command = filter_command(command);
+    if should_exclude_cmd(&filtered_command, config) {
+        return;
+    }
 
-        if !commands_seen.contains(&filtered_command) && !should_exclude_cmd(&filtered_command) {
-            commands_seen.insert(filtered_command.clone());
+    match store.commands_seen.get(&filtered_command) {
+        None => {
+            store.commands_seen.insert(filtered_command.clone(), timestamp);
 
-            flag_command(&filtered_command, flagged_commands);
+            flag_command(&filtered_command, config, &mut store.flagged_commands);
 
             if filtered_command.len() >= 200 {
-                big_commands.insert(filtered_command.len(), filtered_command.clone());
+                store.big_commands.insert(filtered_command.len(), filtered_command.clone());
+            }
+            store.command_map.insert(timestamp, filtered_command);
+        }
+        Some(&previous_timestamp) => {
+            if keep == Keep::Latest && timestamp > previous_timestamp {
+                let emptied = if let Some(commands) = store.command_map.get_vec_mut(&previous_timestamp) {
+                    commands.retain(|existing| existing != &filtered_command);
+                    commands.is_empty()
+                } else {
+                    false
+                };
+                if emptied {
+                    // Don't leave a stale, empty entry behind at the old timestamp.
+                    store.command_map.remove(&previous_timestamp);
+                }
+                store.commands_seen.insert(filtered_command.clone(), timestamp);
+                store.command_map.insert(timestamp, filtered_command);
             }
-            command_map.insert(timestamp, filtered_command);
         }
     }
 }
 
-fn parse_timestamp(line: &str) -> Option<u32> {
-    if BASH_TIMESTAMP_REGEX.is_match(line) {
-        Some(line[1..].parse().unwrap())
-    } else {
-        None
-    }
-}
+fn post_process(output: &str, format: HistoryFormat, store: CommandStore) -> Result<()> {
+    let CommandStore { command_map, big_commands, flagged_commands, .. } = store;
 
-fn post_process(output: &str, is_zsh: bool, mut command_map: BTreeMultiMap<u32, String>, mut big_commands: BTreeMultiMap<usize, String>, mut flagged_commands: HashSet<String>) -> Result<()> {
     let mut big_command_lengths = big_commands.keys().collect::<Vec<&usize>>();
     big_command_lengths.sort();
     for length in big_command_lengths {
@@ -238,135 +229,41 @@ fn post_process(output: &str, is_zsh: bool, mut command_map: BTreeMultiMap<u32,
     let mut output = File::create(output)?;
     for (timestamp, commands) in command_map {
         for command in commands {
-            if is_zsh {
-                output.write_all(format!(": {timestamp}:0;").as_bytes())?;
-            } else {
-                output.write_all(format!("#{timestamp}\n").as_bytes())?;
+            match format {
+                HistoryFormat::Bash => output.write_all(format!("#{timestamp}\n").as_bytes())?,
+                HistoryFormat::ZshExtended => output.write_all(format!(": {timestamp}:0;").as_bytes())?,
+                HistoryFormat::Fish => output.write_all(format!("- cmd: {}\n  when: {timestamp}\n", command.trim_end()).as_bytes())?,
+            }
+            if format != HistoryFormat::Fish {
+                // command already ends in newline
+                output.write_all(command.as_bytes())?;
             }
-            // command already ends in newline
-            output.write_all(command.as_bytes())?;
         }
     }
     Ok(())
 }
 
-// I looked for my most common commands via:
-//    # Omit the timestamp lines in the history file
-//    grep -v '^#' $HISTFILE |
-//      # Use awk to count the first word of every line
-//      awk '{count[$1]++} END {for (word in count) print count[word], word}' |
-//      # Sort numerically, in reverse
-//      sort -rn |
-//      # Take the top 20
-//      head -n 20
-// Here are the 20 most common:
-// 585 cd
-// 485 git
-// 478 l
-// 391 vi
-// 375 rm
-// 371 sk
-// 363 g
-// 287 cat
-// 263 mv
-// 263 docker
-// 243 curl
-// 214 echo
-// 209 cp
-// 203 for
-// 191 fexpr
-// 178 cargo
-// 147 skuba
-// 134 grep
-// 120 gi
-// 107 pbpaste
-// Total == 5607, so over half of the 10,000 commands in my history.
-static EXCLUDE_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
-    vec![
-        // Common commands (which I don't need to save)
-        Regex::new("^echo ").unwrap(),
-        Regex::new("^en ").unwrap(),
-        Regex::new("^cd ").unwrap(),
-        Regex::new("^cd$").unwrap(),
-        Regex::new("^ls ").unwrap(),
-        Regex::new("^ls$").unwrap(),
-        Regex::new("^l ").unwrap(),
-        Regex::new("^l$").unwrap(),
-        Regex::new("^la ").unwrap(),
-        Regex::new("^la$").unwrap(),
-        Regex::new("^lt ").unwrap(),
-        Regex::new("^lt$").unwrap(),
-        Regex::new("^vi ").unwrap(),
-        Regex::new("^md ").unwrap(),
-        Regex::new("^rd ").unwrap(),
-        Regex::new("^mv ").unwrap(),
-        Regex::new("^rm ").unwrap(),
-        Regex::new("^cp ").unwrap(),
-        Regex::new("^ij ").unwrap(),
-        Regex::new("^rr ").unwrap(),
-        Regex::new("^s ").unwrap(),
-        Regex::new("^type ").unwrap(),
-        Regex::new("^sk8s ").unwrap(),
-        Regex::new("^history").unwrap(),
-        Regex::new("^fexpr ").unwrap(),
-        Regex::new("^git add").unwrap(),
-        Regex::new("^git pull").unwrap(),
-        Regex::new("^gpull").unwrap(),
-        Regex::new("^gst").unwrap(),
-        Regex::new("^git status").unwrap(),
-        Regex::new("^git checkout").unwrap(),
-        Regex::new("^git mv").unwrap(),
-        Regex::new("^git rm").unwrap(),
-        Regex::new("^git diff").unwrap(),
-        Regex::new("^git checkout").unwrap(),
-        // All sk8s commands (e.g. 8l, 8h, 8logs)
-        Regex::new("^8").unwrap(),
-        Regex::new("help").unwrap(),
-        // Commands with potential secrets
-        Regex::new("echo.*\\| *pbcopy").unwrap(),
-        Regex::new("en .*\\| *pbcopy").unwrap(),
-        Regex::new("echo.*\\| *clip.exe").unwrap(),
-        Regex::new("en .*\\| *clip.exe").unwrap(),
-        Regex::new("echo.*\\| *base64").unwrap(),
-        Regex::new("en .*\\| *base64").unwrap(),
-    ]
-});
-
-static REPLACEMENTS: Lazy<Vec<(Regex, &str)>> = Lazy::new(|| {
-    vec![
-        (Regex::new("Authorization: Bearer [^'\"]*").unwrap(), "Authorization: Bearer xxx"),
-        (Regex::new("password=\"[^$][^ ]*").unwrap(), "password=XXX"),
-        (Regex::new("password=[^$][^ ]*").unwrap(), "password=XXX"),
-        (Regex::new("password: ?[^ ]*").unwrap(), "password: XXX"),
-    ]
-});
-
-static PATTERNS_TO_FLAG: Lazy<Vec<Regex>> = Lazy::new(|| {
-    vec![
-        Regex::new("password").unwrap(),
-        Regex::new("ssh").unwrap(),
-        Regex::new("secret").unwrap(),
-        Regex::new("base64").unwrap(),
-        Regex::new("jasypt").unwrap(),
-    ]
-});
-
-fn should_exclude_cmd(command: &str) -> bool {
-    EXCLUDE_PATTERNS.iter().any(|regex| {
-        let is_match = regex.is_match(command);
-        if is_match {
-            debug!("Cmd matches {regex}: {}", command.trim_end())
+// The exclude/replacement/flag rules themselves (and the rationale for the built-in defaults)
+// live in `config`, where they're merged with whatever a `--config` file supplies.
+
+fn should_exclude_cmd(command: &str, config: &Config) -> bool {
+    let matches = config.exclude_patterns.matches(command);
+    if matches.matched_any() {
+        for index in matches.iter() {
+            debug!("Cmd matches {}: {}", config.exclude_patterns.patterns()[index], command.trim_end());
         }
-        is_match
-    })
+        true
+    } else {
+        false
+    }
 }
 
-fn filter_command(command: &str) -> String {
+fn filter_command(command: &str, config: &Config) -> String {
     let mut filtered_command: String = command.into();
-    for (regex, replacement) in REPLACEMENTS.iter() {
+    for (regex, replacement) in config.replacements.iter() {
         if regex.is_match(&filtered_command) {
             debug!("Replacing {regex} with {replacement} in {command}");
-            filtered_command = regex.replace(&filtered_command, *replacement).into();
+            filtered_command = regex.replace(&filtered_command, replacement.as_str()).into();
             debug!("Result: {command}");
         }
     }
@@ -374,8 +271,57 @@ fn filter_command(command: &str) -> String {
     filtered_command
 }
 
-fn flag_command(command: &str, flagged_commands: &mut HashSet<String>) {
-    if let Some(regex) = PATTERNS_TO_FLAG.iter().find(|regex| regex.is_match(command)) {
-        flagged_commands.insert(format!("Flagged for '{regex}': {command}"));
+fn flag_command(command: &str, config: &Config, flagged_commands: &mut HashSet<String>) {
+    if let Some(index) = config.flag_patterns.matches(command).iter().next() {
+        let pattern = &config.flag_patterns.patterns()[index];
+        flagged_commands.insert(format!("Flagged for '{pattern}': {command}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> Config {
+        Config::load(None).unwrap()
+    }
+
+    #[test]
+    fn keep_first_leaves_duplicate_at_its_original_timestamp() {
+        let config = test_config();
+        let mut store = CommandStore::default();
+
+        add_command(100, "run_my_pipeline_now\n", &config, Keep::First, &mut store);
+        add_command(200, "run_my_pipeline_now\n", &config, Keep::First, &mut store);
+
+        assert_eq!(store.command_map.get_vec(&100), Some(&vec!["run_my_pipeline_now\n".to_string()]));
+        assert_eq!(store.command_map.get_vec(&200), None);
+        assert_eq!(store.commands_seen.get("run_my_pipeline_now\n"), Some(&100));
+    }
+
+    #[test]
+    fn keep_latest_relocates_duplicate_to_the_newer_timestamp() {
+        let config = test_config();
+        let mut store = CommandStore::default();
+
+        add_command(100, "run_my_pipeline_now\n", &config, Keep::Latest, &mut store);
+        add_command(200, "run_my_pipeline_now\n", &config, Keep::Latest, &mut store);
+
+        assert_eq!(store.command_map.get_vec(&100), None);
+        assert_eq!(store.command_map.get_vec(&200), Some(&vec!["run_my_pipeline_now\n".to_string()]));
+        assert_eq!(store.commands_seen.get("run_my_pipeline_now\n"), Some(&200));
+    }
+
+    #[test]
+    fn keep_latest_ignores_an_out_of_order_earlier_duplicate() {
+        let config = test_config();
+        let mut store = CommandStore::default();
+
+        add_command(200, "run_my_pipeline_now\n", &config, Keep::Latest, &mut store);
+        add_command(100, "run_my_pipeline_now\n", &config, Keep::Latest, &mut store);
+
+        assert_eq!(store.command_map.get_vec(&200), Some(&vec!["run_my_pipeline_now\n".to_string()]));
+        assert_eq!(store.command_map.get_vec(&100), None);
+        assert_eq!(store.commands_seen.get("run_my_pipeline_now\n"), Some(&200));
     }
 }
@@ -0,0 +1,98 @@
+use log::info;
+
+use crate::CommandStore;
+
+const TOP_TOKEN_COUNT: usize = 20;
+const SECONDS_PER_DAY: u32 = 86_400;
+const SECONDS_PER_HOUR: u32 = 3_600;
+
+/// Prints a summary of the history file instead of writing a shrunk copy: how much was kept vs.
+/// dropped, the most common first words, and when the commands were run.
+pub fn print_stats(store: &CommandStore, original_bytes: usize) {
+    let total_unique = store.commands_seen.len();
+    let kept_bytes: usize = store
+        .command_map
+        .keys()
+        .filter_map(|timestamp| store.command_map.get_vec(timestamp))
+        .flatten()
+        .map(|command| command.len())
+        .sum();
+    let bytes_saved = original_bytes.saturating_sub(kept_bytes);
+
+    info!("+=========================+");
+    info!("|      HISTORY STATS      |");
+    info!("+=========================+");
+    info!("{:>7} commands parsed", store.total_lines);
+    info!("{:>7} unique commands kept", total_unique);
+    info!("{:>7} commands redacted for high-entropy secrets", store.count_redacted);
+    info!("{:>7} commands flagged for review", store.flagged_commands.len());
+    info!("{:>7} bytes saved ({original_bytes} -> {kept_bytes})", bytes_saved);
+
+    let mut token_counts: Vec<(&String, &usize)> = store.first_token_counts.iter().collect();
+    token_counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    info!("+=========================+");
+    info!("| TOP {TOP_TOKEN_COUNT} FIRST WORDS          |");
+    info!("+=========================+");
+    for (token, count) in token_counts.into_iter().take(TOP_TOKEN_COUNT) {
+        info!("{count:>7} {token}");
+    }
+
+    let (day_counts, hour_counts) = activity_by_day_and_hour(store);
+    let active_days = day_counts.iter().filter(|&&count| count > 0).count().max(1);
+    let commands_per_day = total_unique as f64 / active_days as f64;
+    let most_active_hour = hour_counts
+        .iter()
+        .enumerate()
+        .max_by_key(|&(_, count)| count)
+        .map(|(hour, _)| hour)
+        .unwrap_or(0);
+
+    info!("+=========================+");
+    info!("{commands_per_day:>7.1} commands/day, averaged over {active_days} active day(s)");
+    info!("{:>7}:00 is the most active hour ({} commands)", most_active_hour, hour_counts[most_active_hour]);
+}
+
+/// Counts, per UTC day-since-epoch and per hour-of-day (0-23), how many commands were kept.
+///
+/// Each timestamp in `command_map` holds a vector of commands, so every command stored under a
+/// timestamp is counted, not just one per timestamp.
+fn activity_by_day_and_hour(store: &CommandStore) -> (Vec<u32>, [u32; 24]) {
+    let mut day_counts: Vec<u32> = Vec::new();
+    let mut hour_counts = [0u32; 24];
+    for &timestamp in store.command_map.keys() {
+        let count = store.command_map.get_vec(&timestamp).map_or(0, |commands| commands.len()) as u32;
+        let day = timestamp / SECONDS_PER_DAY;
+        let hour = ((timestamp % SECONDS_PER_DAY) / SECONDS_PER_HOUR) as usize;
+        if day as usize >= day_counts.len() {
+            day_counts.resize(day as usize + 1, 0);
+        }
+        day_counts[day as usize] += count;
+        hour_counts[hour] += count;
+    }
+    (day_counts, hour_counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_active_hour_counts_commands_not_bytes() {
+        let mut store = CommandStore::default();
+        // One long command at hour 22, but two short commands at hour 23: hour 23 is more
+        // active even though its commands are much shorter.
+        store.command_map.insert(22 * SECONDS_PER_HOUR, "a".repeat(24));
+        store.command_map.insert(23 * SECONDS_PER_HOUR, "b".into());
+        store.command_map.insert(23 * SECONDS_PER_HOUR + 60, "c".into());
+
+        let (day_counts, hour_counts) = activity_by_day_and_hour(&store);
+
+        assert_eq!(day_counts, vec![3]);
+        assert_eq!(hour_counts[22], 1);
+        assert_eq!(hour_counts[23], 2);
+
+        let most_active_hour = hour_counts.iter().enumerate().max_by_key(|&(_, count)| count).map(|(hour, _)| hour);
+        assert_eq!(most_active_hour, Some(23));
+    }
+}